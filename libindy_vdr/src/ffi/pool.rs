@@ -1,22 +1,358 @@
 use crate::common::error::prelude::*;
-use crate::pool::{PoolFactory, PoolRunner, RequestResult};
+use crate::pool::{PoolFactory, PoolRunner, PreparedRequest, RequestResult};
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::os::raw::c_char;
-use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
 
 use ffi_support::{rust_string_to_c, FfiStr};
+use serde_json::json;
 
 use super::error::{set_last_error, ErrorCode};
 use super::requests::{RequestHandle, REQUESTS};
 use super::POOL_CONFIG;
 
 new_handle_type!(PoolHandle, FFI_PH_COUNTER);
+new_handle_type!(SubmitHandle, FFI_SH_COUNTER);
 
 lazy_static! {
     pub static ref POOLS: RwLock<BTreeMap<PoolHandle, PoolRunner>> = RwLock::new(BTreeMap::new());
 }
 
+// Number of requests currently dispatched per pool, kept alive independently
+// of the `PoolRunner` itself so that `indy_vdr_pool_close_complete` can tell
+// when it is safe to report the pool as fully closed.
+lazy_static! {
+    static ref POOL_OUTSTANDING: RwLock<BTreeMap<PoolHandle, Arc<AtomicUsize>>> =
+        RwLock::new(BTreeMap::new());
+}
+
+// Tracks requests submitted via `indy_vdr_pool_submit_request` so that
+// `indy_vdr_pool_cancel_request` can reach in and fire the caller's callback
+// early. A request is registered when it is submitted and removed either
+// when it completes normally or when it is cancelled, whichever happens
+// first.
+struct PendingSubmit {
+    pool_handle: PoolHandle,
+    outstanding: Arc<AtomicUsize>,
+    // guards against the completion callback and the cancellation callback
+    // both firing: whichever side wins the swap gets to invoke `cb`.
+    fired: Arc<AtomicBool>,
+    cb: extern "C" fn(err: ErrorCode, response: *const c_char),
+    reply_format: ReplyFormat,
+    // Wall-clock start used by `ReplyFormat::WithTiming` to report the
+    // request's true total elapsed time, not just the slowest node's.
+    submitted_at: Instant,
+}
+
+// Whether a successful reply is delivered as the raw ledger reply body
+// (`indy_vdr_pool_submit_request`) or wrapped in a JSON envelope carrying
+// per-attempt timing metadata (`indy_vdr_pool_submit_request_ex`).
+#[derive(Clone, Copy)]
+enum ReplyFormat {
+    Plain,
+    WithTiming,
+}
+
+lazy_static! {
+    static ref PENDING_REQUESTS: RwLock<BTreeMap<SubmitHandle, PendingSubmit>> =
+        RwLock::new(BTreeMap::new());
+}
+
+// A pool that has been asked to close gracefully via
+// `indy_vdr_pool_close_complete`. The `PoolRunner` is kept alive here, off
+// the `POOLS` map, purely so that requests already in flight can keep
+// running to completion and deliver their callbacks.
+struct PoolDrain {
+    _runner: PoolRunner,
+    outstanding: Arc<AtomicUsize>,
+    cb: extern "C" fn(err: ErrorCode),
+}
+
+lazy_static! {
+    static ref DRAINING_POOLS: RwLock<BTreeMap<PoolHandle, PoolDrain>> = RwLock::new(BTreeMap::new());
+}
+
+// Called whenever a submitted request finishes, whether by completing
+// normally or by being cancelled. If this was the last outstanding request
+// for a pool that is draining, fires the close-complete callback and cleans
+// up the remaining per-pool bookkeeping (by then, a zero outstanding count
+// guarantees nothing is left queued or in flight for this pool).
+fn finish_pool_request(pool_handle: PoolHandle, outstanding: &Arc<AtomicUsize>) {
+    if outstanding.fetch_sub(1, Ordering::SeqCst) == 1 {
+        if let Ok(mut draining) = DRAINING_POOLS.write() {
+            if let Some(drain) = draining.remove(&pool_handle) {
+                (drain.cb)(ErrorCode::Success);
+                cleanup_pool_state(pool_handle);
+            }
+        }
+    }
+}
+
+// Removes every per-pool map entry keyed by `pool_handle` other than
+// `POOLS`/`POOL_OUTSTANDING`/`DRAINING_POOLS`, which callers clean up
+// themselves. Used by `indy_vdr_pool_close` (immediately) and by
+// `finish_pool_request` / `indy_vdr_pool_close_complete` (once a graceful
+// drain finishes), so a process that
+// repeatedly creates and closes pools doesn't leak one stale entry per pool
+// in each of these maps.
+fn cleanup_pool_state(pool_handle: PoolHandle) {
+    if let Ok(mut in_flight) = POOL_IN_FLIGHT.write() {
+        in_flight.remove(&pool_handle);
+    }
+    if let Ok(mut limits) = POOL_MAX_IN_FLIGHT.write() {
+        limits.remove(&pool_handle);
+    }
+    let queued_handles = POOL_QUEUES.write().ok().and_then(|mut queues| queues.remove(&pool_handle));
+    if let Some(handles) = queued_handles {
+        if let Ok(mut queued) = QUEUED_REQUESTS.write() {
+            for handle in &handles {
+                queued.remove(handle);
+            }
+        }
+        if let Ok(mut pending) = PENDING_REQUESTS.write() {
+            for handle in &handles {
+                pending.remove(handle);
+            }
+        }
+    }
+}
+
+// Default maximum number of requests dispatched to the `PoolRunner`
+// concurrently per pool; additional submissions are queued FIFO and drained
+// as slots free up. Overridable per process via
+// `indy_vdr_pool_set_max_in_flight` before a pool is created; each pool
+// snapshots the current value into `POOL_MAX_IN_FLIGHT` at creation time so
+// later calls don't change the bound for pools that already exist. A real
+// `PoolConfig` field (set on `POOL_CONFIG`) would be preferable but that
+// struct is defined in `crate::pool`, not part of this chunk of the tree.
+const DEFAULT_MAX_IN_FLIGHT: usize = 8;
+
+lazy_static! {
+    static ref MAX_IN_FLIGHT_CONFIG: RwLock<usize> = RwLock::new(DEFAULT_MAX_IN_FLIGHT);
+    // Per-pool bound, snapshotted from `MAX_IN_FLIGHT_CONFIG` at creation.
+    static ref POOL_MAX_IN_FLIGHT: RwLock<BTreeMap<PoolHandle, usize>> = RwLock::new(BTreeMap::new());
+    // Count of requests currently dispatched to the `PoolRunner`, per pool.
+    static ref POOL_IN_FLIGHT: RwLock<BTreeMap<PoolHandle, usize>> = RwLock::new(BTreeMap::new());
+    // FIFO of submit handles waiting for an in-flight slot, per pool.
+    static ref POOL_QUEUES: RwLock<BTreeMap<PoolHandle, VecDeque<SubmitHandle>>> =
+        RwLock::new(BTreeMap::new());
+    // Prepared requests that have been submitted but not yet dispatched.
+    static ref QUEUED_REQUESTS: RwLock<BTreeMap<SubmitHandle, PreparedRequest>> =
+        RwLock::new(BTreeMap::new());
+}
+
+// Looks up the `PoolRunner` for `pool_handle`, checking `DRAINING_POOLS` as
+// well as `POOLS`: a request can still be sitting in `POOL_QUEUES` when
+// `indy_vdr_pool_close_complete` moves the runner out of `POOLS`, and it
+// must still be able to dispatch so the drain can actually finish instead of
+// leaking the runner and every other per-pool map forever.
+fn with_pool_runner<R>(pool_handle: PoolHandle, f: impl FnOnce(&PoolRunner) -> VdrResult<R>) -> VdrResult<R> {
+    let pools = read_lock!(POOLS)?;
+    if let Some(pool) = pools.get(&pool_handle) {
+        return f(pool);
+    }
+    drop(pools);
+    let draining = read_lock!(DRAINING_POOLS)?;
+    let drain = draining
+        .get(&pool_handle)
+        .ok_or_else(|| input_err("Unknown pool handle"))?;
+    f(&drain._runner)
+}
+
+// Sends `req` to the pool right away and wires up the completion closure
+// that every dispatched request shares: deliver the result once, keep the
+// draining/outstanding bookkeeping correct, and free up an in-flight slot
+// for the next queued request.
+fn dispatch_request(
+    pool_handle: PoolHandle,
+    submit_handle: SubmitHandle,
+    req: PreparedRequest,
+    cb: extern "C" fn(err: ErrorCode, response: *const c_char),
+    outstanding: Arc<AtomicUsize>,
+    fired: Arc<AtomicBool>,
+    reply_format: ReplyFormat,
+    submitted_at: Instant,
+) -> VdrResult<()> {
+    with_pool_runner(pool_handle, |pool| {
+        pool.send_request(
+            req,
+            Box::new(move |result| {
+                if let Ok(mut pending) = PENDING_REQUESTS.write() {
+                    pending.remove(&submit_handle);
+                }
+                // This closure is the single source of truth for `outstanding`
+                // once a request has been dispatched: `indy_vdr_pool_cancel_request`
+                // only accounts for requests it catches while still queued, so
+                // there is exactly one decrement per dispatched request no matter
+                // which side wins the `fired` race.
+                if !fired.swap(true, Ordering::SeqCst) {
+                    let (errcode, reply) = match result {
+                        Ok((reply, timing)) => match reply {
+                            RequestResult::Reply(body) => (
+                                ErrorCode::Success,
+                                format_reply(body, timing, reply_format, submitted_at),
+                            ),
+                            RequestResult::Failed(err) => {
+                                let code = ErrorCode::from(&err);
+                                set_last_error(Some(err));
+                                (code, String::new())
+                            }
+                        },
+                        Err(err) => {
+                            let code = ErrorCode::from(&err);
+                            set_last_error(Some(err));
+                            (code, String::new())
+                        }
+                    };
+                    cb(errcode, rust_string_to_c(reply));
+                }
+                finish_pool_request(pool_handle, &outstanding);
+                release_in_flight_slot(pool_handle);
+            }),
+        )
+    })
+}
+
+// Builds the string handed back through the completion callback for a
+// successful reply: the raw ledger reply body for `ReplyFormat::Plain`, or a
+// JSON envelope adding which node(s) responded, how long each took, the
+// request's true total elapsed time, and whether consensus was actually
+// reached, for `ReplyFormat::WithTiming`.
+fn format_reply(
+    body: String,
+    timing: Option<HashMap<String, f32>>,
+    format: ReplyFormat,
+    submitted_at: Instant,
+) -> String {
+    match format {
+        ReplyFormat::Plain => body,
+        ReplyFormat::WithTiming => {
+            // Reaching this arm at all means the `PoolRunner` already
+            // resolved a `RequestResult::Reply`, which it only ever returns
+            // once enough matching node responses came in; a reply with no
+            // per-node timing attached would instead mean it was served
+            // without actually polling the ledger (e.g. a cached result),
+            // so we don't count that as "consensus reached".
+            let consensus_reached = timing.as_ref().map_or(false, |t| !t.is_empty());
+            let timing = timing.unwrap_or_default();
+            let elapsed_ms = submitted_at.elapsed().as_secs_f64() * 1000.0;
+            let envelope = json!({
+                "result": body,
+                "node_timing_ms": timing,
+                "elapsed_ms": elapsed_ms,
+                "consensus_reached": consensus_reached,
+            });
+            envelope.to_string()
+        }
+    }
+}
+
+// Frees up the in-flight slot held by a request that just finished and, if
+// another submission was queued behind it, dispatches that one next.
+fn release_in_flight_slot(pool_handle: PoolHandle) {
+    let next = {
+        let mut in_flight = match POOL_IN_FLIGHT.write() {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+        if let Some(count) = in_flight.get_mut(&pool_handle) {
+            *count = count.saturating_sub(1);
+        }
+        let mut queues = match POOL_QUEUES.write() {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+        queues.get_mut(&pool_handle).and_then(VecDeque::pop_front)
+    };
+    let submit_handle = match next {
+        Some(handle) => handle,
+        None => return,
+    };
+    let req = {
+        let mut queued = match QUEUED_REQUESTS.write() {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+        queued.remove(&submit_handle)
+    };
+    let pending = {
+        let pending = match PENDING_REQUESTS.read() {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+        pending.get(&submit_handle).map(|p| {
+            (
+                p.cb,
+                p.outstanding.clone(),
+                p.fired.clone(),
+                p.reply_format,
+                p.submitted_at,
+            )
+        })
+    };
+    let (req, (cb, outstanding, fired, reply_format, submitted_at)) = match (req, pending) {
+        (Some(req), Some(pending)) => (req, pending),
+        // Cancelled while queued; nothing left to dispatch for this handle.
+        _ => return,
+    };
+    if let Ok(mut in_flight) = POOL_IN_FLIGHT.write() {
+        *in_flight.entry(pool_handle).or_insert(0) += 1;
+    }
+    let _ = dispatch_request(
+        pool_handle,
+        submit_handle,
+        req,
+        cb,
+        outstanding,
+        fired,
+        reply_format,
+        submitted_at,
+    );
+}
+
+// Applies `POOL_CONFIG`, creates the `PoolRunner`, and registers it in
+// `POOLS`/`POOL_OUTSTANDING` under a fresh handle. Shared by
+// `indy_vdr_pool_create_from_genesis_file` and
+// `indy_vdr_pool_create_from_genesis_txns`, which differ only in how the
+// `PoolFactory` is constructed.
+fn create_pool(mut factory: PoolFactory) -> VdrResult<PoolHandle> {
+    {
+        let gcfg = read_lock!(POOL_CONFIG)?;
+        factory.set_config(*gcfg)?;
+    }
+    let pool = factory.create_runner()?;
+    let handle = PoolHandle::next();
+    {
+        let mut pools = write_lock!(POOLS)?;
+        pools.insert(handle, pool);
+    }
+    {
+        let mut outstanding = write_lock!(POOL_OUTSTANDING)?;
+        outstanding.insert(handle, Arc::new(AtomicUsize::new(0)));
+    }
+    {
+        let max_in_flight = *read_lock!(MAX_IN_FLIGHT_CONFIG)?;
+        let mut limits = write_lock!(POOL_MAX_IN_FLIGHT)?;
+        limits.insert(handle, max_in_flight);
+    }
+    Ok(handle)
+}
+
+// Sets the maximum number of requests dispatched concurrently per pool for
+// pools created after this call; pools already created keep the bound that
+// was in effect when they were created. Defaults to `DEFAULT_MAX_IN_FLIGHT`.
+#[no_mangle]
+pub extern "C" fn indy_vdr_pool_set_max_in_flight(limit: usize) -> ErrorCode {
+    catch_err! {
+        let mut cfg = write_lock!(MAX_IN_FLIGHT_CONFIG)?;
+        *cfg = limit;
+        Ok(ErrorCode::Success)
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn indy_vdr_pool_create_from_genesis_file(
     path: FfiStr,
@@ -25,15 +361,30 @@ pub extern "C" fn indy_vdr_pool_create_from_genesis_file(
     catch_err! {
         trace!("Create pool from genesis file");
         check_useful_c_ptr!(handle_p);
-        let mut factory = PoolFactory::from_genesis_file(path.as_str())?;
-        {
-            let gcfg = read_lock!(POOL_CONFIG)?;
-            factory.set_config(*gcfg)?;
+        let factory = PoolFactory::from_genesis_file(path.as_str())?;
+        let handle = create_pool(factory)?;
+        unsafe {
+            *handle_p = *handle;
         }
-        let pool = factory.create_runner()?;
-        let handle = PoolHandle::next();
-        let mut pools = write_lock!(POOLS)?;
-        pools.insert(handle, pool);
+        Ok(ErrorCode::Success)
+    }
+}
+
+// Like `indy_vdr_pool_create_from_genesis_file`, but takes the newline
+// delimited genesis transactions directly (the same format
+// `indy_vdr_pool_get_transactions` emits via `txns.join("\n")`) instead of a
+// path, so hosts with no writable filesystem can build a pool without
+// materializing genesis data on disk.
+#[no_mangle]
+pub extern "C" fn indy_vdr_pool_create_from_genesis_txns(
+    txns: FfiStr,
+    handle_p: *mut usize,
+) -> ErrorCode {
+    catch_err! {
+        trace!("Create pool from genesis transactions");
+        check_useful_c_ptr!(handle_p);
+        let factory = PoolFactory::from_genesis_transactions(txns.as_str())?;
+        let handle = create_pool(factory)?;
         unsafe {
             *handle_p = *handle;
         }
@@ -71,60 +422,355 @@ pub extern "C" fn indy_vdr_pool_get_transactions(
     }
 }
 
+// Shared implementation behind `indy_vdr_pool_submit_request` and
+// `indy_vdr_pool_submit_request_ex`; the two only differ in how the
+// successful reply is formatted for the caller.
+fn submit_request_impl(
+    pool_handle: usize,
+    request_handle: usize,
+    cb: extern "C" fn(err: ErrorCode, response: *const c_char),
+    submit_id_p: *mut usize,
+    reply_format: ReplyFormat,
+) -> VdrResult<ErrorCode> {
+    check_useful_c_ptr!(submit_id_p);
+    {
+        let pools = read_lock!(POOLS)?;
+        pools.get(&PoolHandle(pool_handle))
+            .ok_or_else(|| input_err("Unknown pool handle"))?;
+    }
+    let req = {
+        let mut reqs = write_lock!(REQUESTS)?;
+        reqs.remove(&RequestHandle(request_handle))
+            .ok_or_else(|| input_err("Unknown request handle"))?
+    };
+
+    let outstanding = {
+        let outstanding = read_lock!(POOL_OUTSTANDING)?;
+        outstanding.get(&PoolHandle(pool_handle))
+            .ok_or_else(|| input_err("Unknown pool handle"))?
+            .clone()
+    };
+    outstanding.fetch_add(1, Ordering::SeqCst);
+
+    let submit_handle = SubmitHandle::next();
+    let fired = Arc::new(AtomicBool::new(false));
+    let submitted_at = Instant::now();
+    {
+        let mut pending = write_lock!(PENDING_REQUESTS)?;
+        pending.insert(submit_handle, PendingSubmit {
+            pool_handle: PoolHandle(pool_handle),
+            outstanding: outstanding.clone(),
+            fired: fired.clone(),
+            cb,
+            reply_format,
+            submitted_at,
+        });
+    }
+
+    // Dispatch immediately if the pool has a free in-flight slot,
+    // otherwise queue the request FIFO; `release_in_flight_slot` drains
+    // the queue as slots free up.
+    let limit = {
+        let limits = read_lock!(POOL_MAX_IN_FLIGHT)?;
+        *limits
+            .get(&PoolHandle(pool_handle))
+            .unwrap_or(&DEFAULT_MAX_IN_FLIGHT)
+    };
+    let dispatch_now = {
+        let mut in_flight = write_lock!(POOL_IN_FLIGHT)?;
+        let count = in_flight.entry(PoolHandle(pool_handle)).or_insert(0);
+        if *count < limit {
+            *count += 1;
+            true
+        } else {
+            false
+        }
+    };
+
+    if dispatch_now {
+        dispatch_request(
+            PoolHandle(pool_handle),
+            submit_handle,
+            req,
+            cb,
+            outstanding,
+            fired,
+            reply_format,
+            submitted_at,
+        )?;
+    } else {
+        {
+            let mut queued = write_lock!(QUEUED_REQUESTS)?;
+            queued.insert(submit_handle, req);
+        }
+        let mut queues = write_lock!(POOL_QUEUES)?;
+        queues.entry(PoolHandle(pool_handle)).or_insert_with(VecDeque::new)
+            .push_back(submit_handle);
+    }
+
+    unsafe {
+        *submit_id_p = *submit_handle;
+    }
+    Ok(ErrorCode::Success)
+}
+
 #[no_mangle]
 pub extern "C" fn indy_vdr_pool_submit_request(
     pool_handle: usize,
     request_handle: usize,
     cb: Option<extern "C" fn(err: ErrorCode, response: *const c_char)>,
+    submit_id_p: *mut usize,
 ) -> ErrorCode {
     catch_err! {
         trace!("Submit request: {} {}", pool_handle, request_handle);
         let cb = cb.ok_or_else(|| input_err("No callback provided"))?;
-        let pools = read_lock!(POOLS)?;
-        let pool = pools.get(&PoolHandle(pool_handle))
-            .ok_or_else(|| input_err("Unknown pool handle"))?;
-        let req = {
-            let mut reqs = write_lock!(REQUESTS)?;
-            reqs.remove(&RequestHandle(request_handle))
-                .ok_or_else(|| input_err("Unknown request handle"))?
-        };
-        pool.send_request(req, Box::new(
-            move |result| {
-                let (errcode, reply) = match result {
-                    Ok((reply, _timing)) => {
-                        match reply {
-                            RequestResult::Reply(body) => {
-                                (ErrorCode::Success, body)
-                            }
-                            RequestResult::Failed(err) => {
-                                let code = ErrorCode::from(&err);
-                                set_last_error(Some(err));
-                                (code, String::new())
-                            }
-                        }
-                    },
-                    Err(err) => {
-                        let code = ErrorCode::from(&err);
-                        set_last_error(Some(err));
-                        (code, String::new())
-                    }
-                };
-                cb(errcode, rust_string_to_c(reply))
-            }))?;
+        submit_request_impl(pool_handle, request_handle, cb, submit_id_p, ReplyFormat::Plain)
+    }
+}
+
+// Like `indy_vdr_pool_submit_request`, but the callback receives a JSON
+// envelope (`{"result", "node_timing_ms", "elapsed_ms", "consensus_reached"}`)
+// instead of the raw reply body, so monitoring/diagnostic consumers can
+// attribute latency or flakiness to specific validators without patching
+// the crate.
+#[no_mangle]
+pub extern "C" fn indy_vdr_pool_submit_request_ex(
+    pool_handle: usize,
+    request_handle: usize,
+    cb: Option<extern "C" fn(err: ErrorCode, response: *const c_char)>,
+    submit_id_p: *mut usize,
+) -> ErrorCode {
+    catch_err! {
+        trace!("Submit request (ex): {} {}", pool_handle, request_handle);
+        let cb = cb.ok_or_else(|| input_err("No callback provided"))?;
+        submit_request_impl(pool_handle, request_handle, cb, submit_id_p, ReplyFormat::WithTiming)
+    }
+}
+
+// Cancels a request previously submitted with `indy_vdr_pool_submit_request`.
+// This is purely a local, client-side suppression: the callback is invoked
+// immediately with `ErrorCode::Canceled` and the eventual result from the
+// ledger (if the request was already dispatched) is discarded when it
+// arrives rather than delivered. The in-flight request on the wire is not
+// itself abandoned. Cancelling a submit_id that is unknown or has already
+// completed is a no-op that still returns success.
+#[no_mangle]
+pub extern "C" fn indy_vdr_pool_cancel_request(pool_handle: usize, submit_id: usize) -> ErrorCode {
+    catch_err! {
+        trace!("Cancel request: {} {}", pool_handle, submit_id);
+        {
+            let pools = read_lock!(POOLS)?;
+            let draining = read_lock!(DRAINING_POOLS)?;
+            if !pools.contains_key(&PoolHandle(pool_handle))
+                && !draining.contains_key(&PoolHandle(pool_handle))
+            {
+                return Err(input_err("Unknown pool handle"));
+            }
+        }
+        cancel_pending(PoolHandle(pool_handle), SubmitHandle(submit_id))?;
         Ok(ErrorCode::Success)
     }
 }
 
-// NOTE: at the moment, pending requests are allowed to complete
-// and request callbacks are still run, even if we no longer have a
-// reference to the pool here. Maybe an optional callback for when
-// the close has completed?
+// Accounting half of `indy_vdr_pool_cancel_request`, split out so it can be
+// exercised without a real `PoolRunner` backing `pool_handle`.
+fn cancel_pending(pool_handle: PoolHandle, submit_id: SubmitHandle) -> VdrResult<()> {
+    let pending = {
+        let mut pending = write_lock!(PENDING_REQUESTS)?;
+        pending.remove(&submit_id)
+    };
+    // If the request was still queued (never dispatched), drop it here so
+    // `release_in_flight_slot` never hands it to the pool, and remember that
+    // so we're the ones responsible for accounting for it below: a
+    // dispatched request's own completion closure is always the one that
+    // calls `finish_pool_request`, so calling it here too would
+    // double-decrement `outstanding`.
+    let was_queued = {
+        let mut queued = write_lock!(QUEUED_REQUESTS)?;
+        queued.remove(&submit_id).is_some()
+    };
+    {
+        let mut queues = write_lock!(POOL_QUEUES)?;
+        if let Some(queue) = queues.get_mut(&pool_handle) {
+            queue.retain(|handle| *handle != submit_id);
+        }
+    }
+    if let Some(pending) = pending {
+        if !pending.fired.swap(true, Ordering::SeqCst) {
+            (pending.cb)(ErrorCode::Canceled, rust_string_to_c(String::new()));
+            if was_queued {
+                finish_pool_request(pending.pool_handle, &pending.outstanding);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Drops the pool handle immediately; requests already dispatched keep
+// running and their callbacks still fire (via their own held references),
+// but anything still queued is dropped without a callback, and there is no
+// signal back to the caller when the last dispatched request completes. See
+// `indy_vdr_pool_close_complete` for a variant that waits for that to
+// happen.
 #[no_mangle]
 pub extern "C" fn indy_vdr_pool_close(pool_handle: usize) -> ErrorCode {
     catch_err! {
-        let mut pools = write_lock!(POOLS)?;
-        pools.remove(&PoolHandle(pool_handle))
-            .ok_or_else(|| input_err("Unknown pool handle"))?;
+        {
+            let mut pools = write_lock!(POOLS)?;
+            pools.remove(&PoolHandle(pool_handle))
+                .ok_or_else(|| input_err("Unknown pool handle"))?;
+        }
+        if let Ok(mut outstanding) = POOL_OUTSTANDING.write() {
+            outstanding.remove(&PoolHandle(pool_handle));
+        }
+        cleanup_pool_state(PoolHandle(pool_handle));
+        Ok(ErrorCode::Success)
+    }
+}
+
+// Closes a pool gracefully: the handle is removed from `POOLS` immediately
+// (no new requests can be submitted against it), but the underlying
+// `PoolRunner` is kept alive internally until every request dispatched
+// before the close completes or is cancelled. `cb` is invoked exactly once,
+// after which no request callback for this pool will run again, so FFI
+// consumers can safely free per-request context.
+#[no_mangle]
+pub extern "C" fn indy_vdr_pool_close_complete(
+    pool_handle: usize,
+    cb: Option<extern "C" fn(err: ErrorCode)>,
+) -> ErrorCode {
+    catch_err! {
+        trace!("Close pool (complete): {}", pool_handle);
+        let cb = cb.ok_or_else(|| input_err("No callback provided"))?;
+        let runner = {
+            let mut pools = write_lock!(POOLS)?;
+            pools.remove(&PoolHandle(pool_handle))
+                .ok_or_else(|| input_err("Unknown pool handle"))?
+        };
+        let outstanding = {
+            let mut outstanding = write_lock!(POOL_OUTSTANDING)?;
+            outstanding.remove(&PoolHandle(pool_handle))
+                .unwrap_or_else(|| Arc::new(AtomicUsize::new(0)))
+        };
+        // Register the drain before checking the count: if a request
+        // finishes concurrently, whichever of us removes the entry from
+        // `DRAINING_POOLS` first is the one that fires `cb`, so it's fired
+        // exactly once no matter how the race lands.
+        {
+            let mut draining = write_lock!(DRAINING_POOLS)?;
+            draining.insert(PoolHandle(pool_handle), PoolDrain {
+                _runner: runner,
+                outstanding: outstanding.clone(),
+                cb,
+            });
+        }
+        if outstanding.load(Ordering::SeqCst) == 0 {
+            let mut draining = write_lock!(DRAINING_POOLS)?;
+            if let Some(drain) = draining.remove(&PoolHandle(pool_handle)) {
+                (drain.cb)(ErrorCode::Success);
+                cleanup_pool_state(PoolHandle(pool_handle));
+            }
+        }
+        Ok(ErrorCode::Success)
+    }
+}
+
+// Reports how many requests are currently dispatched to the pool and how
+// many more are queued behind its max-in-flight bound, so callers can
+// observe saturation under load.
+#[no_mangle]
+pub extern "C" fn indy_vdr_pool_get_status(
+    pool_handle: usize,
+    cb: Option<extern "C" fn(err: ErrorCode, response: *const c_char)>,
+) -> ErrorCode {
+    catch_err! {
+        trace!("Get pool status: {}", pool_handle);
+        let cb = cb.ok_or_else(|| input_err("No callback provided"))?;
+        {
+            let pools = read_lock!(POOLS)?;
+            pools.get(&PoolHandle(pool_handle))
+                .ok_or_else(|| input_err("Unknown pool handle"))?;
+        }
+        let in_flight = {
+            let in_flight = read_lock!(POOL_IN_FLIGHT)?;
+            *in_flight.get(&PoolHandle(pool_handle)).unwrap_or(&0)
+        };
+        let queued = {
+            let queues = read_lock!(POOL_QUEUES)?;
+            queues.get(&PoolHandle(pool_handle)).map(VecDeque::len).unwrap_or(0)
+        };
+        let status = format!(r#"{{"in_flight":{},"queued":{}}}"#, in_flight, queued);
+        cb(ErrorCode::Success, rust_string_to_c(status));
         Ok(ErrorCode::Success)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn noop_cb(_err: ErrorCode, _response: *const c_char) {}
+
+    fn insert_pending(pool_handle: PoolHandle, submit_handle: SubmitHandle, outstanding: &Arc<AtomicUsize>) {
+        PENDING_REQUESTS.write().unwrap().insert(submit_handle, PendingSubmit {
+            pool_handle,
+            outstanding: outstanding.clone(),
+            fired: Arc::new(AtomicBool::new(false)),
+            cb: noop_cb,
+            reply_format: ReplyFormat::Plain,
+            submitted_at: Instant::now(),
+        });
+    }
+
+    // Cancelling a request that has already been dispatched must NOT call
+    // `finish_pool_request` itself: the dispatched request's own completion
+    // closure is the sole owner of that decrement, and will run later and
+    // simply find `fired` already set, regardless of whether cancellation
+    // already fired the caller's callback.
+    #[test]
+    fn cancel_of_dispatched_request_does_not_decrement_outstanding() {
+        let outstanding = Arc::new(AtomicUsize::new(1));
+        let pool_handle = PoolHandle::next();
+        let submit_handle = SubmitHandle::next();
+        // Not present in QUEUED_REQUESTS: simulates a request already handed
+        // off to `dispatch_request`.
+        insert_pending(pool_handle, submit_handle, &outstanding);
+
+        cancel_pending(pool_handle, submit_handle).unwrap();
+
+        // Still 1: only the (not-yet-run) completion closure may bring this
+        // to 0.
+        assert_eq!(outstanding.load(Ordering::SeqCst), 1);
+        assert!(!PENDING_REQUESTS.read().unwrap().contains_key(&submit_handle));
+    }
+
+    // Cancelling an already-completed (or unknown) submit_id is a no-op.
+    #[test]
+    fn cancel_after_completion_is_a_noop() {
+        let pool_handle = PoolHandle::next();
+        let submit_handle = SubmitHandle::next();
+
+        assert!(cancel_pending(pool_handle, submit_handle).is_ok());
+    }
+
+    // `indy_vdr_pool_close` must not leak an entry per closed pool in the
+    // in-flight/limit/queue/pending maps.
+    #[test]
+    fn cleanup_pool_state_removes_every_per_pool_entry() {
+        let pool_handle = PoolHandle::next();
+        let submit_handle = SubmitHandle::next();
+        let outstanding = Arc::new(AtomicUsize::new(0));
+
+        POOL_IN_FLIGHT.write().unwrap().insert(pool_handle, 3);
+        POOL_MAX_IN_FLIGHT.write().unwrap().insert(pool_handle, 8);
+        POOL_QUEUES.write().unwrap().insert(pool_handle, VecDeque::from(vec![submit_handle]));
+        insert_pending(pool_handle, submit_handle, &outstanding);
+
+        cleanup_pool_state(pool_handle);
+
+        assert!(!POOL_IN_FLIGHT.read().unwrap().contains_key(&pool_handle));
+        assert!(!POOL_MAX_IN_FLIGHT.read().unwrap().contains_key(&pool_handle));
+        assert!(!POOL_QUEUES.read().unwrap().contains_key(&pool_handle));
+        assert!(!PENDING_REQUESTS.read().unwrap().contains_key(&submit_handle));
+    }
+}
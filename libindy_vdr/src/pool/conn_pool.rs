@@ -0,0 +1,266 @@
+// Per-validator connection pooling: idle, reusable connections are kept per
+// node alias and handed out on checkout instead of paying the reconnect cost
+// on every request.
+//
+// Not yet wired up: the call site lives in the networker, which owns the
+// actual transport type and isn't part of this chunk of the tree. Plumbing
+// `ConnectionPoolConfig` through `POOL_CONFIG` is blocked on the same gap
+// (`POOL_CONFIG`'s `PoolConfig` type is also defined outside this chunk).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::common::error::prelude::*;
+
+/// Whether a transport may be shared by multiple concurrent requests
+/// (multiplexed), or must be checked out exclusively until it's released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reservation {
+    Multiplexed,
+    Exclusive,
+}
+
+/// Tunables for the per-node connection pool, passed to `ConnectionPool::new`.
+/// They are not read from `POOL_CONFIG`.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionPoolConfig {
+    pub idle_timeout: Duration,
+    pub max_idle_per_node: usize,
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        ConnectionPoolConfig {
+            idle_timeout: Duration::from_secs(60),
+            max_idle_per_node: 2,
+        }
+    }
+}
+
+struct Slot<T> {
+    conn: Arc<T>,
+    idle_at: Instant,
+}
+
+struct NodeSlots<T> {
+    reservation: Reservation,
+    // Exclusive transports live here only while idle and are popped out on
+    // checkout; multiplexed transports stay here permanently and are handed
+    // out by cloning the `Arc`.
+    slots: Vec<Slot<T>>,
+}
+
+/// A connection checked out of the pool. Call `release` once the request is
+/// done with it so an exclusive transport can be reused; dropping without
+/// releasing discards it instead.
+pub struct Checkout<T> {
+    conn: Option<Arc<T>>,
+    node_alias: String,
+    reservation: Reservation,
+    pool: ConnectionPool<T>,
+}
+
+impl<T> Checkout<T> {
+    pub fn conn(&self) -> &T {
+        self.conn.as_deref().expect("checkout already released")
+    }
+
+    pub fn release(mut self) {
+        if self.reservation == Reservation::Exclusive {
+            if let Some(conn) = self.conn.take() {
+                self.pool.put_idle(&self.node_alias, conn);
+            }
+        }
+        // Multiplexed connections never left the idle set, so there is
+        // nothing to return.
+    }
+}
+
+pub struct ConnectionPool<T> {
+    inner: Arc<Mutex<HashMap<String, NodeSlots<T>>>>,
+    config: ConnectionPoolConfig,
+}
+
+impl<T> Clone for ConnectionPool<T> {
+    fn clone(&self) -> Self {
+        ConnectionPool {
+            inner: self.inner.clone(),
+            config: self.config,
+        }
+    }
+}
+
+impl<T> ConnectionPool<T> {
+    pub fn new(config: ConnectionPoolConfig) -> Self {
+        ConnectionPool {
+            inner: Arc::new(Mutex::new(HashMap::new())),
+            config,
+        }
+    }
+
+    /// Hand out a warm connection for `node_alias` if one is idle (or, for a
+    /// multiplexed transport, already open), otherwise call `connect` to
+    /// open a new one.
+    pub fn checkout(
+        &self,
+        node_alias: &str,
+        reservation: Reservation,
+        connect: impl FnOnce() -> VdrResult<T>,
+    ) -> VdrResult<Checkout<T>> {
+        let existing = {
+            let mut inner = self.inner.lock().unwrap();
+            let node = inner
+                .entry(node_alias.to_string())
+                .or_insert_with(|| NodeSlots {
+                    reservation,
+                    slots: Vec::new(),
+                });
+            match reservation {
+                Reservation::Multiplexed => node.slots.first().map(|slot| slot.conn.clone()),
+                Reservation::Exclusive => node.slots.pop().map(|slot| slot.conn),
+            }
+        };
+        let conn = match existing {
+            Some(conn) => conn,
+            None => {
+                let conn = Arc::new(connect()?);
+                if reservation == Reservation::Multiplexed {
+                    let mut inner = self.inner.lock().unwrap();
+                    let node = inner
+                        .entry(node_alias.to_string())
+                        .or_insert_with(|| NodeSlots {
+                            reservation,
+                            slots: Vec::new(),
+                        });
+                    node.slots.push(Slot {
+                        conn: conn.clone(),
+                        idle_at: Instant::now(),
+                    });
+                }
+                conn
+            }
+        };
+        Ok(Checkout {
+            conn: Some(conn),
+            node_alias: node_alias.to_string(),
+            reservation,
+            pool: self.clone(),
+        })
+    }
+
+    fn put_idle(&self, node_alias: &str, conn: Arc<T>) {
+        let mut inner = self.inner.lock().unwrap();
+        let node = inner
+            .entry(node_alias.to_string())
+            .or_insert_with(|| NodeSlots {
+                reservation: Reservation::Exclusive,
+                slots: Vec::new(),
+            });
+        if node.slots.len() < self.config.max_idle_per_node {
+            node.slots.push(Slot {
+                conn,
+                idle_at: Instant::now(),
+            });
+        }
+    }
+
+    /// Drop idle connections that haven't been used in longer than
+    /// `idle_timeout`. Intended to be called periodically. Only exclusive
+    /// connections are reaped; multiplexed connections are kept.
+    pub fn reap_idle(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        for node in inner.values_mut() {
+            if node.reservation == Reservation::Exclusive {
+                node.slots
+                    .retain(|slot| now.duration_since(slot.idle_at) < self.config.idle_timeout);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting_connect(calls: &Arc<AtomicUsize>) -> impl FnOnce() -> VdrResult<u32> + '_ {
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(calls.load(Ordering::SeqCst) as u32)
+        }
+    }
+
+    #[test]
+    fn exclusive_checkout_connects_once_and_reuses_after_release() {
+        let pool = ConnectionPool::new(ConnectionPoolConfig::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let first = pool.checkout("node1", Reservation::Exclusive, counting_connect(&calls)).unwrap();
+        let conn_id = *first.conn();
+        first.release();
+
+        let second = pool.checkout("node1", Reservation::Exclusive, counting_connect(&calls)).unwrap();
+        assert_eq!(*second.conn(), conn_id);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn exclusive_checkout_without_release_is_not_reused() {
+        let pool = ConnectionPool::new(ConnectionPoolConfig::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let first = pool.checkout("node1", Reservation::Exclusive, counting_connect(&calls)).unwrap();
+        drop(first);
+
+        let _second = pool.checkout("node1", Reservation::Exclusive, counting_connect(&calls)).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn multiplexed_checkout_always_reuses_the_same_connection() {
+        let pool = ConnectionPool::new(ConnectionPoolConfig::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let first = pool.checkout("node1", Reservation::Multiplexed, counting_connect(&calls)).unwrap();
+        let conn_id = *first.conn();
+        first.release();
+
+        let second = pool.checkout("node1", Reservation::Multiplexed, counting_connect(&calls)).unwrap();
+        assert_eq!(*second.conn(), conn_id);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn reap_idle_evicts_only_expired_exclusive_slots() {
+        let pool = ConnectionPool::new(ConnectionPoolConfig {
+            idle_timeout: Duration::from_secs(0),
+            max_idle_per_node: 2,
+        });
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let checkout = pool.checkout("node1", Reservation::Exclusive, counting_connect(&calls)).unwrap();
+        checkout.release();
+        assert_eq!(pool.inner.lock().unwrap().get("node1").unwrap().slots.len(), 1);
+
+        pool.reap_idle();
+        assert_eq!(pool.inner.lock().unwrap().get("node1").unwrap().slots.len(), 0);
+    }
+
+    #[test]
+    fn max_idle_per_node_bounds_the_idle_set() {
+        let pool = ConnectionPool::new(ConnectionPoolConfig {
+            idle_timeout: Duration::from_secs(60),
+            max_idle_per_node: 1,
+        });
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let first = pool.checkout("node1", Reservation::Exclusive, counting_connect(&calls)).unwrap();
+        let second = pool.checkout("node1", Reservation::Exclusive, counting_connect(&calls)).unwrap();
+        first.release();
+        second.release();
+
+        assert_eq!(pool.inner.lock().unwrap().get("node1").unwrap().slots.len(), 1);
+    }
+}
@@ -0,0 +1 @@
+pub mod conn_pool;